@@ -0,0 +1,33 @@
+/// Default budget, in estimated input tokens, for a single batch's prompt.
+pub const DEFAULT_TOKEN_BUDGET: usize = 6000;
+
+/// Cheap token estimate (characters / 4, rounded up). Not exact, but close
+/// enough to make packing decisions without pulling in a real tokenizer.
+pub fn estimate_tokens(s: &str) -> usize {
+    (s.len() + 3) / 4
+}
+
+/// Greedily packs item indices into batches so that each batch's total
+/// estimated cost stays under `budget`. An item whose own cost already
+/// exceeds the budget still gets a batch of its own rather than being
+/// dropped.
+pub fn pack_into_batches(costs: &[usize], budget: usize) -> Vec<Vec<usize>> {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut current_cost = 0usize;
+
+    for (i, &cost) in costs.iter().enumerate() {
+        if !current.is_empty() && current_cost + cost > budget {
+            batches.push(std::mem::take(&mut current));
+            current_cost = 0;
+        }
+        current.push(i);
+        current_cost += cost;
+    }
+
+    if !current.is_empty() {
+        batches.push(current);
+    }
+
+    batches
+}
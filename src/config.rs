@@ -0,0 +1,219 @@
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+
+use crate::batch::DEFAULT_TOKEN_BUDGET;
+use crate::provider::provider_from_name;
+use crate::AppError;
+
+const CONFIG_FILE: &str = "phpdocgen.toml";
+
+/// Models we know about, purely for the startup warning below; any other
+/// value is accepted as a free-form model name so new releases don't need a
+/// code change.
+const KNOWN_MODELS: &[&str] = &[
+    "claude-3-sonnet-20240229",
+    "claude-3-opus-20240229",
+    "claude-3-haiku-20240307",
+    "gpt-4o",
+    "gpt-4o-mini",
+    "gpt-4-turbo",
+    "codellama",
+    "llama3",
+];
+
+/// How `update_php_file` treats methods that already have a docblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    /// Only generate for methods that have no docblock at all.
+    Fill,
+    /// Also regenerate when the existing docblock is missing `@param`s for
+    /// declared parameters, or an `@return` for a body that returns a value.
+    Improve,
+    /// Regenerate every method's docblock, existing or not.
+    Overwrite,
+}
+
+impl UpdateMode {
+    fn parse(s: &str) -> Result<Self, AppError> {
+        match s {
+            "fill" => Ok(Self::Fill),
+            "improve" => Ok(Self::Improve),
+            "overwrite" => Ok(Self::Overwrite),
+            other => Err(AppError::Config(format!(
+                "unknown mode '{other}', expected one of: fill, improve, overwrite"
+            ))),
+        }
+    }
+}
+
+/// Settings supplied on the command line. Each field wins over its
+/// environment variable and config-file counterpart when present.
+#[derive(Default)]
+pub struct CliOverrides {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub max_tokens: Option<u32>,
+    pub base_url: Option<String>,
+    pub template: Option<String>,
+    pub jobs: Option<usize>,
+    pub dry_run: bool,
+    pub mode: Option<String>,
+    pub token_budget: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FileConfig {
+    provider: Option<String>,
+    api_key: Option<String>,
+    model: Option<String>,
+    max_tokens: Option<u32>,
+    base_url: Option<String>,
+    template: Option<String>,
+    jobs: Option<usize>,
+    mode: Option<String>,
+    token_budget: Option<usize>,
+}
+
+/// Fully resolved, validated settings for a run, merged from CLI flags,
+/// environment variables, and an optional `phpdocgen.toml`, in that order of
+/// precedence.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub provider: String,
+    pub api_key: String,
+    pub model: String,
+    pub max_tokens: u32,
+    pub base_url: Option<String>,
+    pub template: Option<String>,
+    pub jobs: usize,
+    pub dry_run: bool,
+    pub mode: UpdateMode,
+    pub token_budget: usize,
+}
+
+impl Config {
+    /// Resolves settings from CLI flags, then environment variables, then
+    /// `phpdocgen.toml` (if present in the working directory), validating
+    /// the result.
+    pub fn load(overrides: CliOverrides) -> Result<Config, AppError> {
+        let file = load_file_config()?;
+
+        let provider_name = overrides
+            .provider
+            .clone()
+            .or_else(|| env::var("PHPDOCGEN_PROVIDER").ok())
+            .or_else(|| file.provider.clone())
+            .unwrap_or_else(|| "anthropic".to_string());
+
+        let provider = provider_from_name(&provider_name)?;
+
+        let api_key = env::var(provider.env_key())
+            .ok()
+            .or_else(|| file.api_key.clone())
+            .unwrap_or_default();
+        if api_key.is_empty() && provider_name != "ollama" {
+            return Err(AppError::Config(format!(
+                "missing API key for provider '{provider_name}': set ${} or `api_key` in {CONFIG_FILE}",
+                provider.env_key()
+            )));
+        }
+
+        let model = overrides
+            .model
+            .clone()
+            .or_else(|| env::var("PHPDOCGEN_MODEL").ok())
+            .or_else(|| file.model.clone())
+            .unwrap_or_else(|| provider.default_model().to_string());
+        if !KNOWN_MODELS.contains(&model.as_str()) {
+            println!(
+                "Warning: '{model}' is not one of the known models ({}); using it as a free-form model name.",
+                KNOWN_MODELS.join(", ")
+            );
+        }
+
+        let max_tokens = overrides
+            .max_tokens
+            .or_else(|| env::var("PHPDOCGEN_MAX_TOKENS").ok().and_then(|v| v.parse().ok()))
+            .or(file.max_tokens)
+            .unwrap_or(1500);
+        if max_tokens == 0 {
+            return Err(AppError::Config(
+                "`max_tokens` must be a positive integer".to_string(),
+            ));
+        }
+
+        let base_url = overrides
+            .base_url
+            .clone()
+            .or_else(|| env::var("PHPDOCGEN_BASE_URL").ok())
+            .or_else(|| file.base_url.clone());
+        if let Some(url) = &base_url {
+            reqwest::Url::parse(url)
+                .map_err(|e| AppError::Config(format!("`base_url` '{url}' is not a valid URL: {e}")))?;
+        }
+
+        let template = overrides
+            .template
+            .clone()
+            .or_else(|| env::var("PHPDOCGEN_TEMPLATE").ok())
+            .or_else(|| file.template.clone());
+
+        let jobs = overrides
+            .jobs
+            .or_else(|| env::var("PHPDOCGEN_JOBS").ok().and_then(|v| v.parse().ok()))
+            .or(file.jobs)
+            .unwrap_or(4);
+        if jobs == 0 {
+            return Err(AppError::Config(
+                "`jobs` must be a positive integer".to_string(),
+            ));
+        }
+
+        let mode_name = overrides
+            .mode
+            .clone()
+            .or_else(|| env::var("PHPDOCGEN_MODE").ok())
+            .or_else(|| file.mode.clone())
+            .unwrap_or_else(|| "fill".to_string());
+        let mode = UpdateMode::parse(&mode_name)?;
+
+        let token_budget = overrides
+            .token_budget
+            .or_else(|| {
+                env::var("PHPDOCGEN_TOKEN_BUDGET")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+            })
+            .or(file.token_budget)
+            .unwrap_or(DEFAULT_TOKEN_BUDGET);
+        if token_budget == 0 {
+            return Err(AppError::Config(
+                "`token_budget` must be a positive integer".to_string(),
+            ));
+        }
+
+        Ok(Config {
+            provider: provider_name,
+            api_key,
+            model,
+            max_tokens,
+            base_url,
+            template,
+            jobs,
+            dry_run: overrides.dry_run,
+            mode,
+            token_budget,
+        })
+    }
+}
+
+fn load_file_config() -> Result<FileConfig, AppError> {
+    match fs::read_to_string(CONFIG_FILE) {
+        Ok(contents) => toml::from_str(&contents)
+            .map_err(|e| AppError::Config(format!("failed to parse {CONFIG_FILE}: {e}"))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(FileConfig::default()),
+        Err(e) => Err(AppError::from(e)),
+    }
+}
@@ -1,234 +1,305 @@
+mod batch;
+mod config;
+mod parser;
+mod pipeline;
+mod provider;
+mod template;
+
 use dotenvy::dotenv;
-use fancy_regex::Regex;
-use indicatif::{ProgressBar, ProgressStyle};
+use handlebars::Handlebars;
+use indicatif::ProgressBar;
 use reqwest::Client;
-use serde_json::json;
 use std::env;
 use std::fs;
 use std::io;
+use std::path::Path;
 use std::process;
+use std::sync::Arc;
 use thiserror::Error;
 
+use batch::{estimate_tokens, pack_into_batches};
+use config::{CliOverrides, Config, UpdateMode};
+use parser::{docblock_is_incomplete, Method};
+use provider::{provider_from_name, DocProvider};
+use template::{load_template, render_prompt};
+
 #[derive(Error, Debug)]
 enum AppError {
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
-    #[error("Regex error: {0}")]
-    Regex(#[from] fancy_regex::Error),
     #[error("Request error: {0}")]
     Request(#[from] reqwest::Error),
-    #[error("Environment variable error: {0}")]
-    Env(#[from] env::VarError),
     #[error("API response error: {0}")]
     ApiResponse(Box<str>),
+    #[error("Configuration error: {0}")]
+    Config(String),
 }
 
-#[derive(Clone, Debug)]
-struct Method {
-    visibility: String,
-    name: String,
-    parameters: String,
-    body: String,
-    docblock: Option<String>,
-    start_position: usize,
+/// Renders a single method's details in the same layout the prompt template
+/// uses, numbered by its original (not batch-local) index. The prompt itself
+/// is rendered by `render_prompt`; this text only feeds the per-method token
+/// estimate used when packing batches.
+fn format_method(index: usize, method: &Method) -> String {
+    format!(
+        "Method {}:\n\
+        Visibility: {}\n\
+        Name: {}\n\
+        Parameters: {}\n\
+        Body:\n{}\n\
+        Existing docblock (if any):\n{}\n",
+        index + 1,
+        method.visibility,
+        method.name,
+        method.parameters,
+        method.body,
+        method.docblock.as_deref().unwrap_or("None")
+    )
 }
 
-/// Parses a PHP file and extracts method information
-///
-/// # Arguments
-///
-/// * `file_path` - The path to the PHP file to parse
-/// * `pb` - A progress bar to update during parsing
-///
-/// # Returns
-///
-/// A Result containing a vector of Method structs or an AppError
-fn parse_php_file(file_path: &str, pb: &ProgressBar) -> Result<Vec<Method>, AppError> {
-    pb.set_message("Parsing PHP file...");
-    let contents = fs::read_to_string(file_path)?;
-    let method_regex = Regex::new(
-        r"(?ms)(/\*\*.*?\*/\s*)?\s*(public|protected|private)?\s*function\s+(\w+)\s*\((.*?)\)\s*\{(.*?)\n\s*\}",
-    )?;
-
-    let captures: Vec<_> = method_regex.captures_iter(&contents).collect();
-    pb.set_length(captures.len() as u64);
-
-    let methods = captures
-        .into_iter()
-        .filter_map(|cap_result| cap_result.ok())
-        .map(|cap| {
-            pb.inc(1);
-            let docblock = cap.get(1).map(|m| m.as_str().to_string());
-            let visibility = cap
-                .get(2)
-                .map_or("public".to_string(), |m| m.as_str().to_string());
-            let name = cap
-                .get(3)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default();
-            let parameters = cap
-                .get(4)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default();
-            let body = cap
-                .get(5)
-                .map(|m| m.as_str().trim().to_string())
-                .unwrap_or_default();
-            let start_position = cap.get(0).map(|m| m.start()).unwrap_or(0);
-
-            Method {
-                visibility,
-                name,
-                parameters,
-                body,
-                docblock,
-                start_position,
-            }
+/// Sends a single batch of methods to the provider and parses the JSON
+/// response back into `(original_index, docblock)` pairs.
+#[allow(clippy::too_many_arguments)]
+async fn generate_batch(
+    methods: &[Method],
+    indices: &[usize],
+    client: &Client,
+    provider: &dyn DocProvider,
+    config: &Config,
+    handlebars: &Handlebars,
+    prompt_template: &str,
+) -> Result<Vec<(usize, String)>, AppError> {
+    let prompt = render_prompt(handlebars, prompt_template, methods, indices)?;
+    let base_url = config.base_url.as_deref().unwrap_or(provider.default_base_url());
+
+    let response = provider
+        .build_request(
+            client,
+            base_url,
+            &config.api_key,
+            &config.model,
+            config.max_tokens,
+            &prompt,
+        )
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::ApiResponse(
+            format!("API request failed with status: {}", response.status()).into(),
+        ));
+    }
+
+    let response_body: serde_json::Value = response.json().await?;
+    let content = provider.parse_response(response_body)?;
+
+    let parsed: serde_json::Value = serde_json::from_str(content.trim()).map_err(|e| {
+        AppError::ApiResponse(format!("Failed to parse batch response as JSON: {e}").into())
+    })?;
+
+    let object = parsed
+        .as_object()
+        .ok_or_else(|| AppError::ApiResponse("Batch response was not a JSON object".into()))?;
+
+    indices
+        .iter()
+        .map(|&i| {
+            object
+                .get(&(i + 1).to_string())
+                .and_then(|v| v.as_str())
+                .map(|s| (i, s.trim().to_string()))
+                .ok_or_else(|| {
+                    AppError::ApiResponse(
+                        format!("Batch response missing docblock for method {}", i + 1).into(),
+                    )
+                })
         })
-        .collect();
+        .collect()
+}
 
-    pb.finish_with_message("PHP file parsed successfully");
-    Ok(methods)
+/// True if `method` should be (re)documented under `mode`: `Fill` only
+/// targets undocumented methods, `Improve` also targets documented ones
+/// whose docblock is missing params/return, and `Overwrite` targets every
+/// method regardless of its current state.
+fn needs_docblock(method: &Method, mode: UpdateMode) -> bool {
+    match mode {
+        UpdateMode::Fill => method.docblock.is_none(),
+        UpdateMode::Improve => method.docblock.is_none() || docblock_is_incomplete(method),
+        UpdateMode::Overwrite => true,
+    }
 }
 
 /// Generates or updates docblocks for a list of methods
 ///
+/// Only the methods selected by `config.mode` (see [`needs_docblock`]) are
+/// sent to the provider at all, so re-running the tool in `fill` or
+/// `improve` mode doesn't re-request docblocks it already has a good answer
+/// for. Methods that are skipped come back as `None`; `update_php_file`
+/// leaves those spots in the file untouched.
+///
+/// Selected methods are packed into batches that stay under a token budget
+/// (so large files don't overflow a single request's `max_tokens`), and each
+/// batch is requested and parsed independently, keyed by the method's
+/// original index, so one failed or malformed batch can be retried without
+/// disturbing the rest. A batch that still fails after its retry is logged
+/// and skipped rather than aborting the whole file: its methods come back
+/// as `None` in the result, same as a method the current mode skipped on
+/// purpose, so every batch that *did* succeed still gets written back.
+///
 /// # Arguments
 ///
 /// * `methods` - A slice of Method structs to generate docblocks for
 /// * `client` - An HTTP client for making API requests
-/// * `api_key` - The API key for authentication
+/// * `provider` - The LLM backend to send the prompt to
+/// * `config` - Resolved settings (API key, model, max_tokens, base URL, mode) for the request
 /// * `pb` - A progress bar to update during docblock generation
 ///
 /// # Returns
 ///
-/// A Result containing a vector of generated docblocks as strings or an AppError
+/// A Result containing one `Option<String>` per input method (`None` means
+/// "leave this method alone") or an AppError
+#[allow(clippy::too_many_arguments)]
 async fn generate_bulk_documentation(
     methods: &[Method],
     client: &Client,
-    api_key: &str,
+    provider: &dyn DocProvider,
+    config: &Config,
     pb: &ProgressBar,
-) -> Result<Vec<String>, AppError> {
+    handlebars: &Handlebars<'_>,
+    prompt_template: &str,
+) -> Result<Vec<Option<String>>, AppError> {
     pb.set_message("Generating docblocks...");
-    let api_url = "https://api.anthropic.com/v1/messages";
 
-    let methods_str = methods
+    let targets: Vec<usize> = methods
         .iter()
         .enumerate()
-        .map(|(i, method)| {
-            format!(
-                "Method {}:\n\
-                Visibility: {}\n\
-                Name: {}\n\
-                Parameters: {}\n\
-                Body:\n{}\n\
-                Existing docblock (if any):\n{}\n",
-                i + 1,
-                method.visibility,
-                method.name,
-                method.parameters,
-                method.body,
-                method.docblock.as_deref().unwrap_or("None")
-            )
-        })
-        .collect::<Vec<String>>()
-        .join("\n---\n");
-
-    let prompt = format!(
-        "Generate PHP docblocks for the following {} methods. For each method, provide a concise description, \
-        @param tags for each parameter, and @return tag if applicable. If there's an existing docblock, \
-        improve it if it's vague or incomplete. Separate each docblock with '---'.\n\n{}",
-        methods.len(),
-        methods_str
-    );
-
-    pb.set_message("Sending request to Claude AI...");
-    let response = client
-        .post(api_url)
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .json(&json!({
-            "model": "claude-3-sonnet-20240229",
-            "max_tokens": 1500,
-            "messages": [{"role": "user", "content": prompt}]
-        }))
-        .send()
-        .await?;
+        .filter(|(_, method)| needs_docblock(method, config.mode))
+        .map(|(i, _)| i)
+        .collect();
 
-    if response.status().is_success() {
-        pb.set_message("Processing AI response...");
-        let response_body: serde_json::Value = response.json().await?;
-        let content = response_body["content"]
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|obj| obj["text"].as_str())
-            .ok_or_else(|| {
-                AppError::ApiResponse("Failed to extract content from API response".into())
-            })?;
-
-        let docblocks: Vec<String> = content
-            .split("---")
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .collect();
+    let mut docblocks: Vec<Option<String>> = vec![None; methods.len()];
 
-        if docblocks.len() != methods.len() {
-            println!("Warning: Mismatch between number of methods and generated docblocks.");
-            println!("Number of methods: {}", methods.len());
-            println!("Number of generated docblocks: {}", docblocks.len());
-            println!("AI response content:");
-            println!("{}", content);
-
-            let adjusted_docblocks = if docblocks.len() < methods.len() {
-                let mut padded = docblocks;
-                padded.extend(
-                    std::iter::repeat(String::from("/** Generated docblock */"))
-                        .take(methods.len() - padded.len()),
-                );
-                padded
-            } else {
-                docblocks.into_iter().take(methods.len()).collect()
-            };
-
-            println!("Adjusted number of docblocks to match methods. Some docblocks may be missing or incomplete.");
-            pb.finish_with_message("Docblocks generated with warnings");
-            Ok(adjusted_docblocks)
-        } else {
-            pb.finish_with_message("Docblocks generated successfully");
-            Ok(docblocks)
+    if targets.is_empty() {
+        pb.finish_with_message("Nothing to do; every method is already documented");
+        return Ok(docblocks);
+    }
+
+    let costs: Vec<usize> = targets
+        .iter()
+        .map(|&i| estimate_tokens(&format_method(i, &methods[i])) * 2)
+        .collect();
+    let local_batches = pack_into_batches(&costs, config.token_budget);
+    let batches: Vec<Vec<usize>> = local_batches
+        .into_iter()
+        .map(|batch| batch.into_iter().map(|local_i| targets[local_i]).collect())
+        .collect();
+    pb.set_length(batches.len() as u64);
+
+    let mut failed_methods = 0usize;
+
+    for batch_indices in &batches {
+        pb.set_message(format!("Sending request to {}...", provider.name()));
+        let batch_result = generate_batch(
+            methods,
+            batch_indices,
+            client,
+            provider,
+            config,
+            handlebars,
+            prompt_template,
+        )
+        .await;
+        let resolved = match batch_result {
+            Ok(pairs) => Some(pairs),
+            Err(e) => {
+                println!("Warning: batch failed ({e}), retrying once...");
+                match generate_batch(
+                    methods,
+                    batch_indices,
+                    client,
+                    provider,
+                    config,
+                    handlebars,
+                    prompt_template,
+                )
+                .await
+                {
+                    Ok(pairs) => Some(pairs),
+                    Err(e) => {
+                        println!(
+                            "Warning: batch failed again ({e}); leaving {} method(s) undocumented this run",
+                            batch_indices.len()
+                        );
+                        failed_methods += batch_indices.len();
+                        None
+                    }
+                }
+            }
+        };
+
+        if let Some(pairs) = resolved {
+            for (index, docblock) in pairs {
+                docblocks[index] = Some(docblock);
+            }
         }
+        pb.inc(1);
+    }
+
+    if failed_methods == 0 {
+        pb.finish_with_message("Docblocks generated successfully");
     } else {
-        Err(AppError::ApiResponse(
-            format!("API request failed with status: {}", response.status()).into(),
-        ))
+        pb.finish_with_message(format!(
+            "Docblocks generated with {failed_methods} method(s) skipped after repeated failures"
+        ));
     }
+    Ok(docblocks)
 }
 
 /// Updates the PHP file with generated docblocks
 ///
+/// `updates[i]` is the new docblock text for `methods[i]`, or `None` to
+/// leave that method untouched (already documented under `fill`/`improve`).
+/// When a method already had a docblock, its old span is replaced in place
+/// rather than appended, so the tool stays safe to run repeatedly: the
+/// running `offset` tracks the net change in length (which can shrink the
+/// file) rather than only ever growing it.
+///
 /// # Arguments
 ///
 /// * `file_path` - The path to the PHP file to update
-/// * `methods` - A slice of Method structs containing the updated docblocks
+/// * `methods` - The Method structs the docblocks were generated from
+/// * `updates` - One new docblock per method, or `None` to skip it
 /// * `pb` - A progress bar to update during file update
 ///
 /// # Returns
 ///
 /// A Result indicating success or an AppError
-fn update_php_file(file_path: &str, methods: &[Method], pb: &ProgressBar) -> Result<(), AppError> {
+fn update_php_file(
+    file_path: &str,
+    methods: &[Method],
+    updates: &[Option<String>],
+    pb: &ProgressBar,
+) -> Result<(), AppError> {
     pb.set_message("Updating PHP file...");
     pb.set_length(methods.len() as u64);
 
     let mut contents = fs::read_to_string(file_path)?;
-    let mut offset = 0;
+    let mut offset: isize = 0;
 
-    for method in methods.iter() {
+    for (method, update) in methods.iter().zip(updates.iter()) {
         pb.inc(1);
-        let insert_position = method.start_position + offset;
+        let Some(new_docblock) = update else {
+            continue;
+        };
+
+        let insert_position = (method.start_position as isize + offset) as usize;
 
-        if let Some(docblock) = &method.docblock {
-            contents.insert_str(insert_position, &format!("\n{}\n", docblock));
-            offset += docblock.len() + 2; // +2 for the newline characters
+        if let Some(existing) = &method.docblock {
+            let end = insert_position + existing.len();
+            contents.replace_range(insert_position..end, new_docblock);
+            offset += new_docblock.len() as isize - existing.len() as isize;
+        } else {
+            contents.insert_str(insert_position, &format!("\n{}\n", new_docblock));
+            offset += new_docblock.len() as isize + 2; // +2 for the newline characters
         }
     }
 
@@ -238,6 +309,28 @@ fn update_php_file(file_path: &str, methods: &[Method], pb: &ProgressBar) -> Res
     Ok(())
 }
 
+const USAGE: &str = "Usage: phpdocgen <path_to_php_file_or_dir> [--provider anthropic|openai|ollama] [--model name] [--max-tokens N] [--base-url url] [--template path.hbs] [--jobs N] [--mode fill|improve|overwrite] [--token-budget N] [--dry-run]";
+
+/// Consumes and returns the value following a flag, or a `Config` error
+/// naming the offending flag if there isn't one.
+fn next_value<'a>(iter: &mut impl Iterator<Item = &'a String>, flag: &str) -> Result<String, AppError> {
+    iter.next()
+        .cloned()
+        .ok_or_else(|| AppError::Config(format!("{flag} requires a value")))
+}
+
+/// Like `next_value`, but also parses the value into `T`, naming the flag
+/// and the offending input in the error rather than panicking.
+fn next_parsed<'a, T: std::str::FromStr>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    flag: &str,
+) -> Result<T, AppError> {
+    let value = next_value(iter, flag)?;
+    value
+        .parse()
+        .map_err(|_| AppError::Config(format!("{flag} expects a number, got '{value}'")))
+}
+
 #[tokio::main]
 async fn main() -> Result<(), AppError> {
     dotenv().ok();
@@ -245,42 +338,210 @@ async fn main() -> Result<(), AppError> {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 2 {
-        eprintln!("Usage: {} <path_to_php_file>", args[0]);
+        eprintln!("{USAGE}");
         process::exit(1);
     }
 
-    let file_path = &args[1];
+    let mut target: Option<&str> = None;
+    let mut overrides = CliOverrides::default();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--provider" {
+            overrides.provider = Some(next_value(&mut iter, "--provider")?);
+        } else if arg == "--template" {
+            overrides.template = Some(next_value(&mut iter, "--template")?);
+        } else if arg == "--jobs" {
+            overrides.jobs = Some(next_parsed(&mut iter, "--jobs")?);
+        } else if arg == "--model" {
+            overrides.model = Some(next_value(&mut iter, "--model")?);
+        } else if arg == "--max-tokens" {
+            overrides.max_tokens = Some(next_parsed(&mut iter, "--max-tokens")?);
+        } else if arg == "--base-url" {
+            overrides.base_url = Some(next_value(&mut iter, "--base-url")?);
+        } else if arg == "--mode" {
+            overrides.mode = Some(next_value(&mut iter, "--mode")?);
+        } else if arg == "--token-budget" {
+            overrides.token_budget = Some(next_parsed(&mut iter, "--token-budget")?);
+        } else if arg == "--dry-run" {
+            overrides.dry_run = true;
+        } else {
+            target = Some(arg);
+        }
+    }
+
+    let target = target.unwrap_or_else(|| {
+        eprintln!("{USAGE}");
+        process::exit(1);
+    });
+
+    let config = Config::load(overrides)?;
+    let provider = provider_from_name(&config.provider)?;
+    let prompt_template = load_template(config.template.as_deref())?;
+    let mut handlebars = Handlebars::new();
+    handlebars.register_escape_fn(handlebars::no_escape);
+
+    let client = Client::new();
+    let target_path = Path::new(target);
+
+    if target_path.is_dir() {
+        pipeline::process_directory(
+            target_path,
+            Arc::new(client),
+            Arc::from(provider),
+            Arc::new(config),
+            Arc::new(handlebars),
+            Arc::new(prompt_template),
+        )
+        .await?;
+    } else {
+        let pb = ProgressBar::new(100);
+        pb.set_style(pipeline::pb_style());
+
+        pipeline::process_file(
+            target_path,
+            &client,
+            provider.as_ref(),
+            &config,
+            &handlebars,
+            &prompt_template,
+            &pb,
+        )
+        .await?;
 
-    let pb_style = ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
-        .unwrap()
-        .progress_chars("##-");
+        pb.finish_and_clear();
+    }
 
-    let pb = ProgressBar::new(100);
-    pb.set_style(pb_style);
+    println!("All tasks completed. Check the console for any warnings.");
 
-    let methods = parse_php_file(file_path, &pb)?;
+    Ok(())
+}
 
-    println!(
-        "Generating or updating docblocks for {} methods in file: {}",
-        methods.len(),
-        file_path
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_php_file;
+
+    const SOURCE: &str = r#"<?php
+class Foo
+{
+    /**
+     * Already documented.
+     *
+     * @return int
+     */
+    public function documented()
+    {
+        return 1;
+    }
 
-    let client = Client::new();
-    let api_key = env::var("CLAUDE_API_KEY")?;
+    /**
+     * Missing a tag.
+     *
+     * @param mixed $x
+     */
+    public function partiallyDocumented($x, $y)
+    {
+        return $x + $y;
+    }
 
-    let docblocks = generate_bulk_documentation(&methods, &client, &api_key, &pb).await?;
+    public function undocumented($a)
+    {
+        return $a;
+    }
+}
+"#;
+
+    /// Deletes the wrapped path when dropped, including on an unwinding
+    /// panic from a failed assertion, so a test failure doesn't leave a
+    /// stale fixture file behind in the OS temp directory.
+    struct TempPhpFile(std::path::PathBuf);
 
-    let mut updated_methods = methods.clone();
-    for (method, docblock) in updated_methods.iter_mut().zip(docblocks.iter()) {
-        method.docblock = Some(docblock.clone());
+    impl Drop for TempPhpFile {
+        fn drop(&mut self) {
+            fs::remove_file(&self.0).ok();
+        }
     }
 
-    update_php_file(file_path, &updated_methods, &pb)?;
+    fn temp_php_file(name: &str, contents: &str) -> TempPhpFile {
+        let path = std::env::temp_dir().join(format!(
+            "phpdocgen_test_{name}_{}.php",
+            std::process::id()
+        ));
+        fs::write(&path, contents).unwrap();
+        TempPhpFile(path)
+    }
 
-    pb.finish_and_clear();
-    println!("All tasks completed. Check the console for any warnings.");
+    /// Parses `path`, applies `new_text` to every method `mode` selects, and
+    /// writes the result back, returning the file's contents afterward.
+    fn apply_mode(path: &str, mode: UpdateMode, new_text: &str) -> String {
+        let pb = ProgressBar::hidden();
+        let methods = parse_php_file(path, &pb).unwrap();
+        let updates: Vec<Option<String>> = methods
+            .iter()
+            .map(|m| needs_docblock(m, mode).then(|| new_text.to_string()))
+            .collect();
+        update_php_file(path, &methods, &updates, &pb).unwrap();
+        fs::read_to_string(path).unwrap()
+    }
 
-    Ok(())
+    #[test]
+    fn update_php_file_is_idempotent_across_modes() {
+        let file = temp_php_file("idempotent", SOURCE);
+        let path_str = file.0.to_str().unwrap();
+
+        // fill: only the undocumented method is targeted.
+        let after_fill = apply_mode(
+            path_str,
+            UpdateMode::Fill,
+            "/**\n     * Generated.\n     *\n     * @param mixed $a\n     * @return mixed\n     */",
+        );
+        assert_eq!(after_fill.matches("/**").count(), 3);
+        assert_eq!(after_fill.matches("Already documented.").count(), 1);
+        assert_eq!(after_fill.matches("Missing a tag.").count(), 1);
+        assert_eq!(after_fill.matches("Generated.").count(), 1);
+
+        // Running fill again must be a no-op: every method now has a docblock.
+        let after_second_fill = apply_mode(path_str, UpdateMode::Fill, "/** Should not appear. */");
+        assert_eq!(
+            after_second_fill, after_fill,
+            "re-running fill must not duplicate or alter existing docblocks"
+        );
+
+        // improve: only the incomplete docblock (missing a @param) is targeted.
+        let after_improve = apply_mode(
+            path_str,
+            UpdateMode::Improve,
+            "/**\n     * Improved.\n     *\n     * @param mixed $x\n     * @param mixed $y\n     * @return mixed\n     */",
+        );
+        assert_eq!(after_improve.matches("/**").count(), 3);
+        assert_eq!(after_improve.matches("Missing a tag.").count(), 0);
+        assert_eq!(after_improve.matches("Improved.").count(), 1);
+        assert_eq!(after_improve.matches("Already documented.").count(), 1);
+        assert_eq!(after_improve.matches("Generated.").count(), 1);
+
+        // Running improve again must be a no-op: every docblock is now complete.
+        let after_second_improve =
+            apply_mode(path_str, UpdateMode::Improve, "/** Should not appear. */");
+        assert_eq!(
+            after_second_improve, after_improve,
+            "re-running improve must not duplicate or alter already-complete docblocks"
+        );
+
+        // overwrite: every method is targeted, replacing its docblock in place.
+        let after_overwrite = apply_mode(path_str, UpdateMode::Overwrite, "/** Overwritten. */");
+        assert_eq!(after_overwrite.matches("/**").count(), 3);
+        assert_eq!(after_overwrite.matches("Overwritten.").count(), 3);
+        assert_eq!(after_overwrite.matches("Already documented.").count(), 0);
+        assert_eq!(after_overwrite.matches("Improved.").count(), 0);
+        assert_eq!(after_overwrite.matches("Generated.").count(), 0);
+
+        // Running overwrite again must replace in place, not grow a duplicate.
+        let after_second_overwrite =
+            apply_mode(path_str, UpdateMode::Overwrite, "/** Overwritten. */");
+        assert_eq!(
+            after_second_overwrite, after_overwrite,
+            "re-running overwrite must not duplicate the docblock it just wrote"
+        );
+    }
 }
@@ -0,0 +1,578 @@
+use indicatif::ProgressBar;
+use std::fs;
+
+use crate::AppError;
+
+#[derive(Clone, Debug)]
+pub struct Method {
+    pub visibility: String,
+    pub name: String,
+    pub parameters: String,
+    pub body: String,
+    pub docblock: Option<String>,
+    pub start_position: usize,
+    /// The enclosing `class`/`trait`/`interface` name, if any, so the prompt
+    /// can include contextual scope.
+    pub scope: Option<String>,
+}
+
+/// Parses a PHP file and extracts method information
+///
+/// Walks the file with a brace-matching scanner rather than a single regex,
+/// so closures, arrays, and control blocks nested inside a method body don't
+/// truncate it early or corrupt the reinsertion offsets in `update_php_file`.
+///
+/// # Arguments
+///
+/// * `file_path` - The path to the PHP file to parse
+/// * `pb` - A progress bar to update during parsing
+///
+/// # Returns
+///
+/// A Result containing a vector of Method structs or an AppError
+pub fn parse_php_file(file_path: &str, pb: &ProgressBar) -> Result<Vec<Method>, AppError> {
+    pb.set_message("Parsing PHP file...");
+    let contents = fs::read_to_string(file_path)?;
+    let methods = scan_methods(contents.as_bytes());
+    pb.set_length(methods.len() as u64);
+    pb.inc(methods.len() as u64);
+    pb.finish_with_message("PHP file parsed successfully");
+    Ok(methods)
+}
+
+/// Scans `src` for method declarations, tracking brace depth, string/comment
+/// state, and enclosing `class`/`trait`/`interface` blocks as it goes.
+fn scan_methods(src: &[u8]) -> Vec<Method> {
+    let mut methods = Vec::new();
+    let mut scope_stack: Vec<(String, usize)> = Vec::new();
+    let mut brace_depth = 0usize;
+    let len = src.len();
+    let mut i = 0usize;
+
+    while i < len {
+        if let Some(next) = skip_non_code(src, i) {
+            i = next;
+            continue;
+        }
+
+        match src[i] {
+            b'{' => {
+                brace_depth += 1;
+                i += 1;
+            }
+            b'}' => {
+                if matches!(scope_stack.last(), Some((_, depth)) if *depth == brace_depth) {
+                    scope_stack.pop();
+                }
+                brace_depth = brace_depth.saturating_sub(1);
+                i += 1;
+            }
+            _ if word_starts_at(src, i, b"class")
+                || word_starts_at(src, i, b"trait")
+                || word_starts_at(src, i, b"interface") =>
+            {
+                if let Some((name_end, name)) = read_following_identifier(src, i) {
+                    scope_stack.push((name, brace_depth + 1));
+                    i = name_end;
+                } else {
+                    i += 1;
+                }
+            }
+            _ if word_starts_at(src, i, b"function") => {
+                if let Some((sig_end, name, parameters)) = match_function_signature(src, i) {
+                    let mut j = sig_end;
+                    while j < len && src[j] != b'{' && src[j] != b';' {
+                        j += 1;
+                    }
+
+                    if j < len && src[j] == b'{' {
+                        let body_close = find_matching_brace(src, j);
+                        let body = String::from_utf8_lossy(&src[j + 1..body_close])
+                            .trim()
+                            .to_string();
+
+                        let (modifiers_start, visibility) = preceding_modifiers_start(src, i);
+                        let docblock = find_preceding_docblock(src, modifiers_start);
+                        let start_position =
+                            docblock.as_ref().map(|(_, start)| *start).unwrap_or(modifiers_start);
+
+                        methods.push(Method {
+                            visibility,
+                            name,
+                            parameters,
+                            body,
+                            docblock: docblock.map(|(text, _)| text),
+                            start_position,
+                            scope: scope_stack.last().map(|(name, _)| name.clone()),
+                        });
+
+                        i = body_close + 1;
+                    } else {
+                        // Abstract/interface method with no body; skip past the `;`.
+                        i = j + 1;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    methods
+}
+
+/// If `src[i..]` starts a line comment, block comment, or string literal
+/// (single-quoted, double-quoted, or heredoc/nowdoc), returns the index just
+/// past it so the caller can skip over its contents without misreading
+/// braces inside it as code.
+fn skip_non_code(src: &[u8], i: usize) -> Option<usize> {
+    match src[i] {
+        b'\'' => Some(skip_single_quoted(src, i)),
+        b'"' => Some(skip_double_quoted(src, i)),
+        b'/' if src.get(i + 1) == Some(&b'/') => Some(skip_line_comment(src, i)),
+        b'#' if src.get(i + 1) != Some(&b'[') => Some(skip_line_comment(src, i)),
+        b'/' if src.get(i + 1) == Some(&b'*') => Some(skip_block_comment(src, i)),
+        b'<' if src[i..].starts_with(b"<<<") => Some(skip_heredoc(src, i)),
+        _ => None,
+    }
+}
+
+fn skip_single_quoted(src: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < src.len() {
+        match src[i] {
+            b'\\' => i += 2,
+            b'\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    src.len()
+}
+
+fn skip_double_quoted(src: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < src.len() {
+        match src[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    src.len()
+}
+
+fn skip_line_comment(src: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < src.len() && src[i] != b'\n' {
+        i += 1;
+    }
+    i
+}
+
+fn skip_block_comment(src: &[u8], start: usize) -> usize {
+    let mut i = start + 2;
+    while i + 1 < src.len() {
+        if src[i] == b'*' && src[i + 1] == b'/' {
+            return i + 2;
+        }
+        i += 1;
+    }
+    src.len()
+}
+
+/// Skips a heredoc/nowdoc body, ending at the end of the first line whose
+/// (optionally indented) content begins with the opening identifier as a
+/// whole word, so `EOT;` closes an `EOT` heredoc.
+fn skip_heredoc(src: &[u8], start: usize) -> usize {
+    let mut i = start + 3;
+    while i < src.len() && (src[i] == b' ' || src[i] == b'\t') {
+        i += 1;
+    }
+    let quoted = matches!(src.get(i), Some(b'\'') | Some(b'"'));
+    if quoted {
+        i += 1;
+    }
+    let ident_start = i;
+    while i < src.len() && (src[i].is_ascii_alphanumeric() || src[i] == b'_') {
+        i += 1;
+    }
+    let ident = src[ident_start..i].to_vec();
+    if quoted && i < src.len() {
+        i += 1;
+    }
+    while i < src.len() && src[i] != b'\n' {
+        i += 1;
+    }
+    i += 1;
+
+    loop {
+        if i >= src.len() {
+            return src.len();
+        }
+        let mut j = i;
+        while j < src.len() && (src[j] == b' ' || src[j] == b'\t') {
+            j += 1;
+        }
+        if src[j..].starts_with(&ident[..]) {
+            let after = j + ident.len();
+            let boundary_ok = after >= src.len()
+                || !(src[after].is_ascii_alphanumeric() || src[after] == b'_');
+            if boundary_ok {
+                let mut k = after;
+                while k < src.len() && src[k] != b'\n' {
+                    k += 1;
+                }
+                return k;
+            }
+        }
+        while i < src.len() && src[i] != b'\n' {
+            i += 1;
+        }
+        i += 1;
+    }
+}
+
+/// True if `keyword` occurs at `i` as a whole word (not a substring of a
+/// longer identifier).
+fn word_starts_at(src: &[u8], i: usize, keyword: &[u8]) -> bool {
+    if i > 0 && (src[i - 1].is_ascii_alphanumeric() || src[i - 1] == b'_') {
+        return false;
+    }
+    if !src[i..].starts_with(keyword) {
+        return false;
+    }
+    let after = i + keyword.len();
+    after >= src.len() || !(src[after].is_ascii_alphanumeric() || src[after] == b'_')
+}
+
+/// Reads the identifier following a `class`/`trait`/`interface` keyword at
+/// `i`, returning the position just past it.
+fn read_following_identifier(src: &[u8], i: usize) -> Option<(usize, String)> {
+    let mut j = i;
+    while j < src.len() && (src[j].is_ascii_alphanumeric() || src[j] == b'_') {
+        j += 1;
+    }
+    while j < src.len() && src[j].is_ascii_whitespace() {
+        j += 1;
+    }
+    let name_start = j;
+    while j < src.len() && (src[j].is_ascii_alphanumeric() || src[j] == b'_') {
+        j += 1;
+    }
+    if j == name_start {
+        return None;
+    }
+    Some((j, String::from_utf8_lossy(&src[name_start..j]).to_string()))
+}
+
+/// Parses a `function` signature starting at `i`, returning the position
+/// just past the closing `)`, the method name, and the raw parameter list.
+/// Returns `None` for anonymous closures (`function (...)`, no name).
+fn match_function_signature(src: &[u8], i: usize) -> Option<(usize, String, String)> {
+    let mut j = i + "function".len();
+    while j < src.len() && src[j].is_ascii_whitespace() {
+        j += 1;
+    }
+    if j < src.len() && src[j] == b'&' {
+        j += 1;
+        while j < src.len() && src[j].is_ascii_whitespace() {
+            j += 1;
+        }
+    }
+
+    let name_start = j;
+    while j < src.len() && (src[j].is_ascii_alphanumeric() || src[j] == b'_') {
+        j += 1;
+    }
+    if j == name_start {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&src[name_start..j]).to_string();
+
+    while j < src.len() && src[j].is_ascii_whitespace() {
+        j += 1;
+    }
+    if j >= src.len() || src[j] != b'(' {
+        return None;
+    }
+
+    let params_close = find_matching_paren(src, j);
+    let parameters = String::from_utf8_lossy(&src[j + 1..params_close])
+        .trim()
+        .to_string();
+
+    Some((params_close + 1, name, parameters))
+}
+
+/// Walks backward from `function_start` over `public`/`protected`/`private`/
+/// `static`/`abstract`/`final` modifiers, returning where they begin (or
+/// `function_start` itself if there are none) and the resolved visibility.
+fn preceding_modifiers_start(src: &[u8], function_start: usize) -> (usize, String) {
+    let mut j = function_start;
+    let mut visibility = None;
+
+    loop {
+        let mut word_end = j;
+        while word_end > 0 && src[word_end - 1].is_ascii_whitespace() {
+            word_end -= 1;
+        }
+        let mut word_start = word_end;
+        while word_start > 0
+            && (src[word_start - 1].is_ascii_alphanumeric() || src[word_start - 1] == b'_')
+        {
+            word_start -= 1;
+        }
+        if word_start == word_end {
+            break;
+        }
+
+        match &src[word_start..word_end] {
+            b"public" | b"protected" | b"private" => {
+                visibility = Some(String::from_utf8_lossy(&src[word_start..word_end]).to_string());
+                j = word_start;
+            }
+            b"static" | b"abstract" | b"final" => {
+                j = word_start;
+            }
+            _ => break,
+        }
+    }
+
+    (j, visibility.unwrap_or_else(|| "public".to_string()))
+}
+
+/// Looks for a `/** ... */` docblock immediately (modulo whitespace) before
+/// `before`, returning its text and start position if found.
+fn find_preceding_docblock(src: &[u8], before: usize) -> Option<(String, usize)> {
+    let mut end = before;
+    while end > 0 && src[end - 1].is_ascii_whitespace() {
+        end -= 1;
+    }
+    if end < 4 || &src[end - 2..end] != b"*/" {
+        return None;
+    }
+
+    let mut i = end - 3;
+    loop {
+        if src[i] == b'/' && src.get(i + 1) == Some(&b'*') {
+            // Require `/**`, not just `/*`, so a plain block comment sitting
+            // above a method isn't mistaken for its docblock.
+            if src.get(i + 2) != Some(&b'*') {
+                return None;
+            }
+            let text = String::from_utf8_lossy(&src[i..end]).to_string();
+            return Some((text, i));
+        }
+        if i == 0 {
+            return None;
+        }
+        i -= 1;
+    }
+}
+
+fn find_matching_paren(src: &[u8], open: usize) -> usize {
+    let mut depth = 0i32;
+    let mut i = open;
+    while i < src.len() {
+        if let Some(next) = skip_non_code(src, i) {
+            i = next;
+            continue;
+        }
+        match src[i] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    src.len().saturating_sub(1)
+}
+
+fn find_matching_brace(src: &[u8], open: usize) -> usize {
+    let mut depth = 0i32;
+    let mut i = open;
+    while i < src.len() {
+        if let Some(next) = skip_non_code(src, i) {
+            i = next;
+            continue;
+        }
+        match src[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    src.len().saturating_sub(1)
+}
+
+/// True if `method` has an existing docblock but it's missing an `@param`
+/// for one of its declared parameters, or an `@return` for a body that
+/// returns a value. Also true when the method has no docblock at all. Used
+/// by `UpdateMode::Improve` to decide whether a method that's already
+/// documented still needs regenerating.
+pub fn docblock_is_incomplete(method: &Method) -> bool {
+    let Some(docblock) = &method.docblock else {
+        return true;
+    };
+
+    let expected_params = count_top_level_commas(&method.parameters);
+    let given_params = docblock.matches("@param").count();
+    if expected_params > given_params {
+        return true;
+    }
+
+    has_value_return(method.body.as_bytes()) && !docblock.contains("@return")
+}
+
+/// Counts comma-separated parameters in a raw parameter list, ignoring
+/// commas nested inside `()`/`[]`/`{}` (e.g. an array default value) or a
+/// string literal.
+fn count_top_level_commas(parameters: &str) -> usize {
+    if parameters.trim().is_empty() {
+        return 0;
+    }
+
+    let src = parameters.as_bytes();
+    let mut depth = 0i32;
+    let mut count = 1usize;
+    let mut i = 0;
+    while i < src.len() {
+        if let Some(next) = skip_non_code(src, i) {
+            i = next;
+            continue;
+        }
+        match src[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b',' if depth == 0 => count += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    count
+}
+
+/// True if `body` contains a `return` statement followed by a value (as
+/// opposed to a bare `return;`).
+fn has_value_return(body: &[u8]) -> bool {
+    let mut i = 0;
+    while i < body.len() {
+        if let Some(next) = skip_non_code(body, i) {
+            i = next;
+            continue;
+        }
+        if word_starts_at(body, i, b"return") {
+            let mut j = i + "return".len();
+            while j < body.len() && body[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j < body.len() && body[j] != b';' {
+                return true;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method_named<'a>(methods: &'a [Method], name: &str) -> &'a Method {
+        methods
+            .iter()
+            .find(|m| m.name == name)
+            .unwrap_or_else(|| panic!("no method named {name}"))
+    }
+
+    #[test]
+    fn handles_nested_braces_and_closures_in_body() {
+        let src = br#"<?php
+        class Foo {
+            public function bar() {
+                $cb = function () {
+                    if (true) {
+                        return 1;
+                    }
+                };
+                return $cb();
+            }
+        }
+        "#;
+        let methods = scan_methods(src);
+        let method = method_named(&methods, "bar");
+        assert!(method.body.contains("function ()"));
+        assert!(method.body.trim_end().ends_with("return $cb();"));
+    }
+
+    #[test]
+    fn handles_closure_default_parameter_value() {
+        let src = br#"<?php
+        class Foo {
+            public function bar($cb = function () { return 1; }) {
+                return $cb();
+            }
+        }
+        "#;
+        let methods = scan_methods(src);
+        let method = method_named(&methods, "bar");
+        assert_eq!(method.parameters, "$cb = function () { return 1; }");
+    }
+
+    #[test]
+    fn ignores_braces_and_quotes_inside_strings_and_heredoc() {
+        let src = br#"<?php
+        class Foo {
+            public function bar() {
+                $a = "{not a brace} \" still inside";
+                $b = <<<EOT
+                { still not a brace either
+                EOT;
+                return $a . $b;
+            }
+        }
+        "#;
+        let methods = scan_methods(src);
+        let method = method_named(&methods, "bar");
+        assert!(method.body.contains("still inside"));
+        assert!(method.body.contains("still not a brace either"));
+    }
+
+    #[test]
+    fn distinguishes_docblock_from_plain_comment() {
+        let src = br#"<?php
+        class Foo {
+            /** Real docblock. */
+            public function documented() {
+                return 1;
+            }
+
+            /* Just a comment, not a docblock. */
+            public function undocumented() {
+                return 1;
+            }
+        }
+        "#;
+        let methods = scan_methods(src);
+        let documented = method_named(&methods, "documented");
+        let undocumented = method_named(&methods, "undocumented");
+        assert_eq!(
+            documented.docblock.as_deref(),
+            Some("/** Real docblock. */")
+        );
+        assert!(undocumented.docblock.is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use handlebars::Handlebars;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::Client;
+use tokio::sync::Semaphore;
+use walkdir::WalkDir;
+
+use crate::config::Config;
+use crate::parser::parse_php_file;
+use crate::provider::DocProvider;
+use crate::{generate_bulk_documentation, update_php_file, AppError};
+
+/// The progress bar style shared by every file's bar, whether there's one
+/// bar for a single file or one per file in a `MultiProgress` group.
+pub fn pb_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+        .unwrap()
+        .progress_chars("##-")
+}
+
+/// Recursively collects every `*.php` file under `root`.
+pub fn find_php_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "php"))
+        .collect()
+}
+
+/// Parses, documents, and (unless `dry_run`) rewrites a single PHP file,
+/// reporting progress on `pb`.
+pub async fn process_file(
+    file_path: &Path,
+    client: &Client,
+    provider: &dyn DocProvider,
+    config: &Config,
+    handlebars: &Handlebars<'_>,
+    prompt_template: &str,
+    pb: &ProgressBar,
+) -> Result<(), AppError> {
+    let file_path_str = file_path.to_string_lossy().into_owned();
+    let methods = parse_php_file(&file_path_str, pb)?;
+
+    if methods.is_empty() {
+        pb.finish_with_message("No methods found");
+        return Ok(());
+    }
+
+    let updates = generate_bulk_documentation(
+        &methods,
+        client,
+        provider,
+        config,
+        pb,
+        handlebars,
+        prompt_template,
+    )
+    .await?;
+
+    if config.dry_run {
+        for (method, update) in methods.iter().zip(updates.iter()) {
+            if let Some(docblock) = update {
+                println!("--- {file_path_str} :: {} ---\n{docblock}\n", method.name);
+            }
+        }
+        pb.finish_with_message("Dry run complete (no files written)");
+    } else {
+        update_php_file(&file_path_str, &methods, &updates, pb)?;
+    }
+
+    Ok(())
+}
+
+/// Runs `process_file` over every PHP file under `root`, bounded to `jobs`
+/// files in flight at once, with one progress bar per file in a shared
+/// `MultiProgress` group.
+pub async fn process_directory(
+    root: &Path,
+    client: Arc<Client>,
+    provider: Arc<dyn DocProvider + Send + Sync>,
+    config: Arc<Config>,
+    handlebars: Arc<Handlebars<'static>>,
+    prompt_template: Arc<String>,
+) -> Result<(), AppError> {
+    let files = find_php_files(root);
+    if files.is_empty() {
+        println!("No .php files found under {}", root.display());
+        return Ok(());
+    }
+
+    let multi = MultiProgress::new();
+    let style = pb_style();
+    let semaphore = Arc::new(Semaphore::new(config.jobs.max(1)));
+
+    let mut tasks = Vec::with_capacity(files.len());
+    for file in files {
+        let permit = Arc::clone(&semaphore);
+        let client = Arc::clone(&client);
+        let provider = Arc::clone(&provider);
+        let config = Arc::clone(&config);
+        let handlebars = Arc::clone(&handlebars);
+        let prompt_template = Arc::clone(&prompt_template);
+
+        let pb = multi.add(ProgressBar::new(100));
+        pb.set_style(style.clone());
+        pb.set_message(file.display().to_string());
+
+        tasks.push(tokio::spawn(async move {
+            let _permit = permit.acquire_owned().await.expect("semaphore closed");
+            let result = process_file(
+                &file,
+                &client,
+                provider.as_ref(),
+                &config,
+                &handlebars,
+                &prompt_template,
+                &pb,
+            )
+            .await;
+
+            if let Err(e) = &result {
+                pb.finish_with_message(format!("Failed: {e}"));
+            }
+            (file, result)
+        }));
+    }
+
+    let mut failures = Vec::new();
+    for task in tasks {
+        let (file, result) = task
+            .await
+            .map_err(|e| AppError::ApiResponse(format!("Task panicked: {e}").into()))?;
+        if let Err(e) = result {
+            eprintln!("Error processing {}: {e}", file.display());
+            failures.push(file);
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::ApiResponse(
+            format!("{} file(s) failed to process", failures.len()).into(),
+        ))
+    }
+}
@@ -0,0 +1,208 @@
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+use crate::AppError;
+
+/// Abstracts over the different LLM backends that can turn a prompt into a
+/// docblock, so the request/response plumbing for each vendor lives in one
+/// place instead of being hard-coded into `generate_bulk_documentation`.
+pub trait DocProvider {
+    /// Short, human-readable name used in logs and the `--provider` flag.
+    fn name(&self) -> &'static str;
+
+    /// Name of the environment variable this provider reads its API key from.
+    fn env_key(&self) -> &'static str;
+
+    /// The default model to use when the caller doesn't override one.
+    fn default_model(&self) -> &'static str;
+
+    /// The default endpoint to send requests to when the caller doesn't
+    /// override one via `Config`. This is always the full request URL
+    /// (scheme, host, and path) — `--base-url`/`PHPDOCGEN_BASE_URL` replace
+    /// it wholesale, so every provider's override has the same contract.
+    fn default_base_url(&self) -> &'static str;
+
+    /// Builds the outgoing HTTP request for `prompt` against `base_url`
+    /// (the full endpoint URL, see `default_base_url`), including auth
+    /// headers and the vendor-specific JSON body.
+    fn build_request(
+        &self,
+        client: &Client,
+        base_url: &str,
+        api_key: &str,
+        model: &str,
+        max_tokens: u32,
+        prompt: &str,
+    ) -> RequestBuilder;
+
+    /// Extracts the generated text from the provider's JSON response body.
+    fn parse_response(&self, body: Value) -> Result<String, AppError>;
+}
+
+/// The original Anthropic Messages API behavior.
+pub struct AnthropicProvider;
+
+impl DocProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn env_key(&self) -> &'static str {
+        "CLAUDE_API_KEY"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "claude-3-sonnet-20240229"
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        "https://api.anthropic.com/v1/messages"
+    }
+
+    fn build_request(
+        &self,
+        client: &Client,
+        base_url: &str,
+        api_key: &str,
+        model: &str,
+        max_tokens: u32,
+        prompt: &str,
+    ) -> RequestBuilder {
+        client
+            .post(base_url)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", "2023-06-01")
+            .json(&json!({
+                "model": model,
+                "max_tokens": max_tokens,
+                "messages": [{"role": "user", "content": prompt}]
+            }))
+    }
+
+    fn parse_response(&self, body: Value) -> Result<String, AppError> {
+        body["content"]
+            .as_array()
+            .and_then(|arr| arr.first())
+            .and_then(|obj| obj["text"].as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| {
+                AppError::ApiResponse("Failed to extract content from API response".into())
+            })
+    }
+}
+
+/// OpenAI's chat-completions endpoint.
+pub struct OpenAiProvider;
+
+impl DocProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn env_key(&self) -> &'static str {
+        "OPENAI_API_KEY"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "gpt-4o-mini"
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        "https://api.openai.com/v1/chat/completions"
+    }
+
+    fn build_request(
+        &self,
+        client: &Client,
+        base_url: &str,
+        api_key: &str,
+        model: &str,
+        max_tokens: u32,
+        prompt: &str,
+    ) -> RequestBuilder {
+        client
+            .post(base_url)
+            .bearer_auth(api_key)
+            .json(&json!({
+                "model": model,
+                "max_tokens": max_tokens,
+                "messages": [{"role": "user", "content": prompt}]
+            }))
+    }
+
+    fn parse_response(&self, body: Value) -> Result<String, AppError> {
+        body["choices"]
+            .as_array()
+            .and_then(|arr| arr.first())
+            .and_then(|choice| choice["message"]["content"].as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| {
+                AppError::ApiResponse("Failed to extract content from API response".into())
+            })
+    }
+}
+
+/// A locally running Ollama server's `/api/chat` endpoint. Ollama has no API
+/// key, so `env_key` names a variable that's only read if set, rather than
+/// being required like the other providers'.
+pub struct OllamaProvider;
+
+impl DocProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn env_key(&self) -> &'static str {
+        "OLLAMA_API_KEY"
+    }
+
+    fn default_model(&self) -> &'static str {
+        "codellama"
+    }
+
+    fn default_base_url(&self) -> &'static str {
+        "http://localhost:11434/api/chat"
+    }
+
+    fn build_request(
+        &self,
+        client: &Client,
+        base_url: &str,
+        _api_key: &str,
+        model: &str,
+        _max_tokens: u32,
+        prompt: &str,
+    ) -> RequestBuilder {
+        client.post(base_url).json(&json!({
+            "model": model,
+            "stream": false,
+            "messages": [{"role": "user", "content": prompt}]
+        }))
+    }
+
+    fn parse_response(&self, body: Value) -> Result<String, AppError> {
+        body["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| {
+                AppError::ApiResponse("Failed to extract content from API response".into())
+            })
+    }
+}
+
+/// Resolves a `--provider`/config value to a concrete [`DocProvider`]. The
+/// `Send + Sync` bound lets the result be shared across the concurrent file
+/// tasks spawned in directory mode.
+pub fn provider_from_name(name: &str) -> Result<Box<dyn DocProvider + Send + Sync>, AppError> {
+    match name {
+        "anthropic" => Ok(Box::new(AnthropicProvider)),
+        "openai" => Ok(Box::new(OpenAiProvider)),
+        "ollama" => Ok(Box::new(OllamaProvider)),
+        other => Err(AppError::ApiResponse(
+            format!(
+                "unknown provider '{other}', expected one of: anthropic, openai, ollama"
+            )
+            .into(),
+        )),
+    }
+}
@@ -0,0 +1,68 @@
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::fs;
+
+use crate::{AppError, Method};
+
+/// Prompt template shipped with the binary, used when no `--template` path
+/// is given. Asks for a JSON object mapping each method's number to its
+/// docblock, which `generate_batch` parses back by original index.
+const DEFAULT_TEMPLATE: &str = include_str!("../templates/default.hbs");
+
+#[derive(Serialize)]
+struct MethodContext<'a> {
+    index: usize,
+    visibility: &'a str,
+    name: &'a str,
+    parameters: &'a str,
+    body: &'a str,
+    docblock: &'a str,
+    scope: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct PromptContext<'a> {
+    methods: Vec<MethodContext<'a>>,
+}
+
+/// Loads a prompt template from `path`, falling back to the embedded default
+/// when no path is given. Keeping the prompt in a template makes house
+/// docblock conventions (e.g. `@throws`, `{@inheritDoc}`, a non-English
+/// description) a config change instead of a recompile.
+pub fn load_template(path: Option<&str>) -> Result<String, AppError> {
+    match path {
+        Some(path) => Ok(fs::read_to_string(path)?),
+        None => Ok(DEFAULT_TEMPLATE.to_string()),
+    }
+}
+
+/// Renders `template` with the methods at `indices`, numbered by their
+/// original (not batch-local) position so responses can be matched back up.
+pub fn render_prompt(
+    handlebars: &Handlebars,
+    template: &str,
+    methods: &[Method],
+    indices: &[usize],
+) -> Result<String, AppError> {
+    let context = PromptContext {
+        methods: indices
+            .iter()
+            .map(|&i| {
+                let method = &methods[i];
+                MethodContext {
+                    index: i + 1,
+                    visibility: &method.visibility,
+                    name: &method.name,
+                    parameters: &method.parameters,
+                    body: &method.body,
+                    docblock: method.docblock.as_deref().unwrap_or("None"),
+                    scope: method.scope.as_deref(),
+                }
+            })
+            .collect(),
+    };
+
+    handlebars
+        .render_template(template, &context)
+        .map_err(|e| AppError::ApiResponse(format!("Failed to render prompt template: {e}").into()))
+}